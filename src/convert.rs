@@ -0,0 +1,293 @@
+//! Flattens the `svd_parser` object model into the [`crate::types`] model
+//! the code generator consumes.
+
+use svd_parser::svd;
+
+use crate::types::*;
+
+pub fn convert(device: &svd::Device) -> ModelDevice {
+    let mut peripherals = Vec::new();
+    let mut instances = Vec::new();
+    let mut interrupts = Vec::new();
+    let mut seen_interrupts = std::collections::BTreeSet::new();
+
+    for peripheral in &device.peripherals {
+        for interrupt in &peripheral.interrupt {
+            // Dedup by number, not by (name, number): the emitted enum gives
+            // each interrupt an explicit discriminant equal to its number, so
+            // two differently-named interrupts sharing a number (common for
+            // combined/shared IRQ lines) would otherwise produce two variants
+            // with the same discriminant, which is a hard compile error.
+            // Shared peripherals (e.g. derivedFrom siblings) also repeat the
+            // same <interrupt> block per instance; keep only the first.
+            if seen_interrupts.insert(interrupt.value) {
+                interrupts.push(ModelInterrupt {
+                    name: interrupt.name.clone(),
+                    value: interrupt.value as u16,
+                    description: interrupt.description.clone(),
+                });
+            }
+        }
+    }
+
+    for peripheral in &device.peripherals {
+        if peripheral.derived_from.is_some() {
+            // Derived peripherals share their parent's register layout and
+            // only contribute a new instance.
+        } else {
+            peripherals.push(convert_peripheral(peripheral));
+        }
+
+        let peripheral_module = derived_module_name(device, peripheral);
+
+        instances.push(ModelPeripheralInstance {
+            name: peripheral.name.clone(),
+            module_name: module_name(&peripheral.name),
+            description: peripheral.description.clone().unwrap_or_default(),
+            peripheral_module,
+            base_address: peripheral.base_address,
+            reset_values: reset_value_entries(resolved_registers(device, peripheral)),
+        });
+    }
+
+    interrupts.sort_by_key(|i| i.value);
+
+    ModelDevice {
+        peripherals,
+        instances,
+        interrupts,
+    }
+}
+
+fn derived_module_name(device: &svd::Device, peripheral: &svd::Peripheral) -> String {
+    match &peripheral.derived_from {
+        Some(parent_name) => {
+            let parent = device
+                .peripherals
+                .iter()
+                .find(|p| &p.name == parent_name)
+                .unwrap_or_else(|| panic!("{} derives from unknown peripheral {}", peripheral.name, parent_name));
+            module_name(&parent.name)
+        }
+        None => module_name(&peripheral.name),
+    }
+}
+
+/// Resolves `peripheral`'s registers, following `derivedFrom` to the parent
+/// when `peripheral` doesn't redeclare its own `<registers>` (the common
+/// case for a `derivedFrom` peripheral that only overrides its name/base
+/// address/interrupt).
+fn resolved_registers<'a>(device: &'a svd::Device, peripheral: &'a svd::Peripheral) -> &'a [svd::RegisterCluster] {
+    if let Some(registers) = peripheral.registers.as_deref() {
+        return registers;
+    }
+
+    match &peripheral.derived_from {
+        Some(parent_name) => {
+            let parent = device
+                .peripherals
+                .iter()
+                .find(|p| &p.name == parent_name)
+                .unwrap_or_else(|| panic!("{} derives from unknown peripheral {}", peripheral.name, parent_name));
+            resolved_registers(device, parent)
+        }
+        None => &[],
+    }
+}
+
+fn convert_peripheral(peripheral: &svd::Peripheral) -> ModelPeripheral {
+    let registers = peripheral
+        .registers
+        .as_deref()
+        .unwrap_or(&[])
+        .iter()
+        .map(|rc| convert_register_cluster(peripheral, rc))
+        .collect();
+
+    ModelPeripheral {
+        name: peripheral.name.clone(),
+        module_name: module_name(&peripheral.name),
+        description: peripheral.description.clone().unwrap_or_default(),
+        registers,
+    }
+}
+
+fn convert_register_cluster(peripheral: &svd::Peripheral, rc: &svd::RegisterCluster) -> RegisterOrCluster {
+    match rc {
+        svd::RegisterCluster::Register(register) => RegisterOrCluster::Register(convert_register(peripheral, register)),
+        svd::RegisterCluster::Cluster(cluster) => RegisterOrCluster::Cluster(convert_cluster(peripheral, cluster)),
+    }
+}
+
+fn convert_register(peripheral: &svd::Peripheral, register: &svd::Register) -> FinalRegisterInfo {
+    let (info, array) = match register {
+        svd::Register::Single(info) => (info, None),
+        svd::Register::Array(info, dim) => (info, Some(array_info(dim))),
+    };
+
+    let access = match info.properties.access.unwrap_or(svd::Access::ReadWrite) {
+        svd::Access::ReadOnly => AccessType::ReadOnly,
+        svd::Access::WriteOnly | svd::Access::WriteOnce => AccessType::WriteOnly,
+        _ => AccessType::ReadWrite,
+    };
+    let size = info.properties.size.unwrap_or(32);
+
+    let mut fields = Vec::new();
+    for field in info.fields() {
+        fields.push(convert_field(peripheral, info, field));
+    }
+
+    FinalRegisterInfo {
+        name: array_element_name(&info.name),
+        description: info.description.clone(),
+        offset: info.address_offset,
+        properties: RegisterProperties { access, size },
+        fields,
+        array,
+    }
+}
+
+fn convert_cluster(peripheral: &svd::Peripheral, cluster: &svd::Cluster) -> FinalClusterInfo {
+    let (info, array) = match cluster {
+        svd::Cluster::Single(info) => (info, None),
+        svd::Cluster::Array(info, dim) => (info, Some(array_info(dim))),
+    };
+
+    let registers = info
+        .children
+        .iter()
+        .map(|rc| convert_register_cluster(peripheral, rc))
+        .collect();
+
+    FinalClusterInfo {
+        name: array_element_name(&info.name),
+        description: info.description.clone(),
+        offset: info.address_offset,
+        registers,
+        array,
+    }
+}
+
+fn array_info(dim: &svd::DimElement) -> ArrayInfo {
+    ArrayInfo {
+        count: dim.dim,
+        stride: dim.dim_increment,
+    }
+}
+
+/// SVD array names carry a `%s` placeholder (e.g. `CH[%s]`, `CH%s`); the
+/// generated field is a plain Rust array, so the placeholder is dropped and
+/// indexing happens with `[]` instead.
+fn array_element_name(name: &str) -> String {
+    name.replace("[%s]", "").replace("%s", "")
+}
+
+fn convert_field(peripheral: &svd::Peripheral, register: &svd::RegisterInfo, field: &svd::Field) -> FinalFieldInfo {
+    let mut enumerated_values = Vec::new();
+    for group in &field.enumerated_values {
+        let group = resolve_enumerated_values(peripheral, register, field, group);
+        let usage = match group.usage.unwrap_or(svd::Usage::ReadWrite) {
+            svd::Usage::Read => ValueUsage::Read,
+            svd::Usage::Write => ValueUsage::Write,
+            svd::Usage::ReadWrite => ValueUsage::ReadWrite,
+        };
+
+        for value in &group.values {
+            // `isDefault` entries describe the catch-all encoding rather than
+            // a single concrete value; they have no single `u32` to emit.
+            let Some(const_value) = value.value else { continue };
+
+            enumerated_values.push(FinalEnumeratedValue {
+                name: value_const_name(&value.name),
+                description: value.description.clone(),
+                value: const_value,
+                usage,
+            });
+        }
+    }
+
+    FinalFieldInfo {
+        name: field.name.clone(),
+        description: field.description.clone(),
+        bit_range: BitRange {
+            offset: field.bit_range.offset,
+            width: field.bit_range.width,
+        },
+        enumerated_values,
+    }
+}
+
+/// Resolves a `<enumeratedValues derivedFrom="...">` reference.
+///
+/// `derivedFrom` may be a bare name (same register), `register.values`, or
+/// `peripheral.register.values`; we only need to search within the same
+/// register and peripheral, which covers what real-world SVDs use.
+fn resolve_enumerated_values<'a>(
+    peripheral: &'a svd::Peripheral,
+    register: &'a svd::RegisterInfo,
+    field: &'a svd::Field,
+    group: &'a svd::EnumeratedValues,
+) -> &'a svd::EnumeratedValues {
+    let Some(derived_from) = group.derived_from.as_ref() else {
+        return group;
+    };
+
+    let name = derived_from.rsplit('.').next().unwrap_or(derived_from);
+
+    field
+        .enumerated_values
+        .iter()
+        .find(|g| g.name.as_deref() == Some(name))
+        .or_else(|| {
+            register
+                .fields()
+                .flat_map(|f| f.enumerated_values.iter())
+                .find(|g| g.name.as_deref() == Some(name))
+        })
+        .or_else(|| {
+            peripheral
+                .registers()
+                .flat_map(|r| r.fields())
+                .flat_map(|f| f.enumerated_values.iter())
+                .find(|g| g.name.as_deref() == Some(name))
+        })
+        .unwrap_or_else(|| panic!("could not resolve enumeratedValues derivedFrom=\"{}\"", derived_from))
+}
+
+fn reset_value_entries(items: &[svd::RegisterCluster]) -> Vec<ResetValueEntry> {
+    items
+        .iter()
+        .map(|rc| match rc {
+            svd::RegisterCluster::Register(register) => {
+                let (info, array) = match register {
+                    svd::Register::Single(info) => (info, None),
+                    svd::Register::Array(info, dim) => (info, Some(array_info(dim))),
+                };
+                ResetValueEntry::Register(ResetValue {
+                    register: array_element_name(&info.name),
+                    value: info.properties.reset_value.unwrap_or(0),
+                    count: array.map(|a| a.count),
+                })
+            }
+            svd::RegisterCluster::Cluster(cluster) => {
+                let (info, array) = match cluster {
+                    svd::Cluster::Single(info) => (info, None),
+                    svd::Cluster::Array(info, dim) => (info, Some(array_info(dim))),
+                };
+                ResetValueEntry::Cluster(ClusterResetValues {
+                    name: array_element_name(&info.name),
+                    array,
+                    entries: reset_value_entries(&info.children),
+                })
+            }
+        })
+        .collect()
+}
+
+fn module_name(name: &str) -> String {
+    name.to_ascii_uppercase()
+}
+
+fn value_const_name(name: &str) -> String {
+    name.to_string()
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::fmt::Write as _;
 use std::fs;
 use std::io::Write;
@@ -14,6 +15,8 @@ pub struct GeneratorConfig {
     pub address_size: AddressSize,
     pub ignore: Vec<String>,
     pub arch_crate: String,
+    pub critical_section: bool,
+    pub backend: RegisterBackend,
 }
 
 impl GeneratorConfig {
@@ -38,6 +41,25 @@ impl GeneratorConfig {
         self.arch_crate = path.into();
         self
     }
+
+    /// Guard `take()`/`release()` with the portable `critical-section` crate
+    /// instead of `{arch_crate}::interrupt::free`. Use this for targets (e.g.
+    /// RISC-V) whose arch crate doesn't provide a cortex-m-style
+    /// `interrupt::free`; the generated RAL then only depends on whichever
+    /// `critical-section` impl the final binary links in.
+    pub fn critical_section(mut self, enabled: bool) -> Self {
+        self.critical_section = enabled;
+        self
+    }
+
+    /// Selects what `RORegister`/`WORegister`/`RWRegister` are and where
+    /// they come from. Defaults to [`RegisterBackend::RalRegisters`], which
+    /// depends on the external `ral_registers` crate; the other variants
+    /// emit a self-contained `registers.rs` into the soc dir instead.
+    pub fn backend(mut self, backend: RegisterBackend) -> Self {
+        self.backend = backend;
+        self
+    }
 }
 
 impl Default for GeneratorConfig {
@@ -46,10 +68,31 @@ impl Default for GeneratorConfig {
             address_size: AddressSize::U32,
             ignore: Vec::new(),
             arch_crate: "crate::arch".to_string(),
+            critical_section: false,
+            backend: RegisterBackend::RalRegisters,
         }
     }
 }
 
+/// Where the generated `RORegister`/`WORegister`/`RWRegister` wrapper types
+/// come from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegisterBackend {
+    /// `use ral_registers::{...};` — the default, matches every RAL crate
+    /// published so far.
+    RalRegisters,
+    /// Emit a `VolatileCell`-based wrapper once per soc into `registers.rs`,
+    /// removing the `ral_registers` dependency entirely.
+    VolatileCell,
+    /// Emit a raw-pointer `read_volatile`/`write_volatile` wrapper once per
+    /// soc into `registers.rs`. Unlike `VolatileCell`, accesses go straight
+    /// through `read_volatile`/`write_volatile` on the register's own
+    /// address instead of delegating to a shared `VolatileCell<T>` type —
+    /// still backed by `UnsafeCell`, since writing through `&self` to a bare
+    /// `T` is undefined behavior regardless of volatility.
+    RawPointer,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum AddressSize {
     U32,
@@ -110,22 +153,52 @@ pub mod peripherals;
 /// Peripheral instances shared by multiple devices
 pub(crate) mod instances;
 
+/// Interrupt numbering
+pub mod interrupts;
+pub use self::interrupts::Interrupt;
+
 /// Metadata
 pub mod metadata;
 "
     )?;
 
-    let device = crate::convert::convert(&device);
+    if config.backend != RegisterBackend::RalRegisters {
+        writeln!(mod_rs, "/// Register access wrappers\npub mod registers;")?;
+        let mut registers_rs = fs::File::create(soc_dir.join("registers.rs"))?;
+        write_registers_module(&mut registers_rs, config.backend)?;
+    }
+
+    let mut device = crate::convert::convert(&device);
+
+    // Structural dedup: peripherals whose registers/fields are identical
+    // (modulo the peripheral's own name/base address/description) are
+    // collapsed onto a single generated module, keyed by whichever of them
+    // is encountered first. `derivedFrom` already makes `convert` point
+    // derived peripherals at their parent's module; this pass additionally
+    // catches peripherals that happen to be identical without declaring it.
+    let canonical_module = dedup_peripheral_modules(&device.peripherals, &config.ignore);
+    for instance in &mut device.instances {
+        if let Some(canonical) = canonical_module.get(&instance.peripheral_module) {
+            instance.peripheral_module = canonical.clone();
+        }
+    }
 
     let mut peripheral_modules = Vec::new();
     let mut instance_modules = Vec::new();
     let mut instance_names = Vec::new();
+    let mut peripheral_rows = Vec::new();
 
     for peripheral in &device.peripherals {
         if config.ignore.iter().any(|v| v == &peripheral.name) {
             continue;
         }
 
+        if canonical_module.get(&peripheral.module_name).is_some_and(|c| c != &peripheral.module_name) {
+            // A structurally identical peripheral earlier in the device
+            // already emitted this module.
+            continue;
+        }
+
         writeln!(peripherals_mod_rs, "pub mod {};", peripheral.module_name)?;
 
         //generate_peripheral(&peripherals_dir, &instances_dir, &device, peripheral)?;
@@ -134,9 +207,22 @@ pub mod metadata;
 
         write_peripheral(&mut peripheral_rs, peripheral, config)?;
 
+        for register in &peripheral.registers {
+            if let RegisterOrCluster::Register(reg_info) = register {
+                peripheral_rows.push((
+                    peripheral.module_name.clone(),
+                    reg_info.name.clone(),
+                    reg_info.offset,
+                    reg_info.properties.access_type_name(),
+                ));
+            }
+        }
+
         peripheral_modules.push(peripheral.module_name.clone());
     }
 
+    let mut instance_rows = Vec::new();
+
     for instance in &device.instances {
         if config.ignore.iter().any(|v| v == &instance.name) {
             continue;
@@ -150,6 +236,7 @@ pub mod metadata;
 
         write_peripheral_instance(&mut instance_rs, instance, config)?;
 
+        instance_rows.push((instance.name.clone(), instance.peripheral_module.clone(), instance.base_address));
         instance_modules.push(instance.module_name.clone());
         instance_names.push(instance.name.clone());
     }
@@ -168,76 +255,450 @@ pub mod metadata;
     for name in &instance_names {
         writeln!(metadata_rs, "    \"{}\",", name)?;
     }
-    writeln!(metadata_rs, "];")?;
+    writeln!(metadata_rs, "];\n")?;
+    writeln!(metadata_rs, "pub const INTERRUPTS: &[(&str, u16)] = &[")?;
+    for interrupt in &device.interrupts {
+        writeln!(metadata_rs, "    (\"{}\", {}),", interrupt.name, interrupt.value)?;
+    }
+    writeln!(metadata_rs, "];\n")?;
+
+    write_foreach_instance(&mut metadata_rs, &instance_rows)?;
+    write_foreach_peripheral(&mut metadata_rs, &peripheral_rows)?;
+
+    let mut interrupts_rs = fs::File::create(soc_dir.join("interrupts.rs"))?;
+    write_interrupts(&mut interrupts_rs, &device.interrupts)?;
 
     Ok(())
 }
 
-fn write_peripheral(file: &mut fs::File, peripheral: &ModelPeripheral, config: &GeneratorConfig) -> Result<()> {
+/// Maps every non-ignored peripheral's module name to the module name that
+/// should actually be generated for it, collapsing structurally identical
+/// peripherals (see [`ModelPeripheral::fingerprint`]) onto a single one.
+fn dedup_peripheral_modules(peripherals: &[ModelPeripheral], ignore: &[String]) -> HashMap<String, String> {
+    let mut canonical_by_fingerprint: HashMap<String, String> = HashMap::new();
+    let mut canonical_module = HashMap::new();
+
+    for peripheral in peripherals {
+        // Ignored peripherals never emit a module, so they can't be canonical.
+        if ignore.iter().any(|v| v == &peripheral.name) {
+            continue;
+        }
+        let canonical = canonical_by_fingerprint
+            .entry(peripheral.fingerprint())
+            .or_insert_with(|| peripheral.module_name.clone());
+        canonical_module.insert(peripheral.module_name.clone(), canonical.clone());
+    }
+
+    canonical_module
+}
+
+/// Writes the self-contained `registers.rs` used when
+/// [`GeneratorConfig::backend`] isn't [`RegisterBackend::RalRegisters`]:
+/// `RORegister`/`WORegister`/`RWRegister` wrapper types offering `.read()`
+/// (`RORegister`, `RWRegister`) and `.write()` (`WORegister`, `RWRegister`),
+/// so `write_peripheral`'s output doesn't otherwise change. No `.modify()`
+/// method is generated.
+fn write_registers_module(file: &mut fs::File, backend: RegisterBackend) -> Result<()> {
     writeln!(file, "#![allow(non_snake_case, non_upper_case_globals)]")?;
-    writeln!(file, "#![allow(non_camel_case_types)]")?;
-    writeln!(file, "{}", build_doc_comment("//!", &peripheral.description))?;
+    writeln!(file, "//! Self-contained register access wrappers (no external dependency).\n")?;
+
+    match backend {
+        RegisterBackend::RalRegisters => unreachable!("only called for self-contained backends"),
+        RegisterBackend::VolatileCell => {
+            writeln!(
+                file,
+                "use core::cell::UnsafeCell;
+
+/// A memory location that hardware may modify concurrently with the CPU.
+#[repr(transparent)]
+pub struct VolatileCell<T> {{
+    value: UnsafeCell<T>,
+}}
 
-    let mut register_modules = Vec::new();
-    let mut register_block = Vec::new();
-    let mut reset_values = Vec::new();
-    let mut access_types = BTreeSet::new();
-    let mut register_types = Vec::new();
+impl<T: Copy> VolatileCell<T> {{
+    #[inline(always)]
+    fn get(&self) -> T {{
+        unsafe {{ core::ptr::read_volatile(self.value.get()) }}
+    }}
 
-    for reg_info in &peripheral.registers {
-        let access_type_name = reg_info.properties.access_type_name();
-        access_types.insert(access_type_name);
+    #[inline(always)]
+    fn set(&self, value: T) {{
+        unsafe {{ core::ptr::write_volatile(self.value.get(), value) }}
+    }}
+}}
 
-        let size_type_name = reg_info.properties.size_type_name();
+unsafe impl<T> Sync for VolatileCell<T> {{}}
 
-        register_types.push(reg_info.name.clone());
+#[repr(transparent)]
+pub struct RORegister<T> {{
+    register: VolatileCell<T>,
+}}
 
-        // Register module
-        let mut code = String::new();
-        if let Some(description) = reg_info.description.as_ref() {
-            let doc = build_doc_comment("///", description);
-            code += &doc;
-        }
-        writeln!(code, "pub mod {} {{", reg_info.name)?;
-        let mut field_strings = Vec::new();
-        for field in &reg_info.fields {
-            field_strings.push(field.generate_code())
+impl<T: Copy> RORegister<T> {{
+    #[inline(always)]
+    pub fn read(&self) -> T {{
+        self.register.get()
+    }}
+}}
+
+#[repr(transparent)]
+pub struct WORegister<T> {{
+    register: VolatileCell<T>,
+}}
+
+impl<T: Copy> WORegister<T> {{
+    #[inline(always)]
+    pub fn write(&self, value: T) {{
+        self.register.set(value)
+    }}
+}}
+
+#[repr(transparent)]
+pub struct RWRegister<T> {{
+    register: VolatileCell<T>,
+}}
+
+impl<T: Copy> RWRegister<T> {{
+    #[inline(always)]
+    pub fn read(&self) -> T {{
+        self.register.get()
+    }}
+
+    #[inline(always)]
+    pub fn write(&self, value: T) {{
+        self.register.set(value)
+    }}
+}}
+"
+            )?;
         }
-        code += &field_strings.join("\n");
-        writeln!(code, "}}")?;
-        register_modules.push(code);
-
-        // RegisterBlock entry
-        let mut s = String::new();
-        if let Some(description) = reg_info.description.as_ref() {
-            let doc = build_doc_comment("    ///", description);
-            s += &doc;
+        RegisterBackend::RawPointer => {
+            writeln!(
+                file,
+                "use core::cell::UnsafeCell;
+
+/// A register wrapper that accesses its value through
+/// `read_volatile`/`write_volatile` directly on the register's own address,
+/// with no intermediate `VolatileCell` type.
+/// Still backed by `UnsafeCell`: writing through `&self` to a bare `T` field
+/// is undefined behavior even when the write itself is volatile.
+#[repr(transparent)]
+pub struct RORegister<T> {{
+    register: UnsafeCell<T>,
+}}
+
+impl<T: Copy> RORegister<T> {{
+    #[inline(always)]
+    pub fn read(&self) -> T {{
+        unsafe {{ core::ptr::read_volatile(self.register.get()) }}
+    }}
+}}
+
+unsafe impl<T> Sync for RORegister<T> {{}}
+
+#[repr(transparent)]
+pub struct WORegister<T> {{
+    register: UnsafeCell<T>,
+}}
+
+impl<T: Copy> WORegister<T> {{
+    #[inline(always)]
+    pub fn write(&self, value: T) {{
+        unsafe {{ core::ptr::write_volatile(self.register.get(), value) }}
+    }}
+}}
+
+unsafe impl<T> Sync for WORegister<T> {{}}
+
+#[repr(transparent)]
+pub struct RWRegister<T> {{
+    register: UnsafeCell<T>,
+}}
+
+impl<T: Copy> RWRegister<T> {{
+    #[inline(always)]
+    pub fn read(&self) -> T {{
+        unsafe {{ core::ptr::read_volatile(self.register.get()) }}
+    }}
+
+    #[inline(always)]
+    pub fn write(&self, value: T) {{
+        unsafe {{ core::ptr::write_volatile(self.register.get(), value) }}
+    }}
+}}
+
+unsafe impl<T> Sync for RWRegister<T> {{}}
+"
+            )?;
         }
+    }
+
+    Ok(())
+}
+
+/// Writes `foreach_instance!`, an X-macro table of every peripheral
+/// instance in the device: `(name, peripheral_module, base_address)`. A
+/// downstream HAL calls it with a `($n:ident, $p:ident, $addr:expr) => {
+/// ... }` arm to generate trait impls/pin mappings per instance at compile
+/// time, without reflection over `INSTANCE_NAMES`.
+fn write_foreach_instance(file: &mut fs::File, rows: &[(String, String, u64)]) -> Result<()> {
+    writeln!(
+        file,
+        "
+/// `foreach_instance!(($n:ident, $p:ident, $addr:expr) => {{ ... }});`
+/// expands the body once for every peripheral instance in this device.
+#[macro_export]
+macro_rules! foreach_instance {{
+    ($pat:tt => $code:tt) => {{
+        macro_rules! __foreach_instance_inner {{
+            $pat => $code;
+        }}"
+    )?;
+    for (name, module, base_address) in rows {
+        writeln!(file, "        __foreach_instance_inner!({}, {}, {:#x});", name, module, base_address)?;
+    }
+    writeln!(file, "    }};\n}}")?;
+
+    Ok(())
+}
+
+/// Writes `foreach_peripheral!`, an X-macro table of the top-level registers
+/// of every generated peripheral module: `(module, register_name, offset,
+/// access_type)`. Registers inside clusters are not listed. Mirrors
+/// `foreach_instance!` but at register granularity.
+fn write_foreach_peripheral(file: &mut fs::File, rows: &[(String, String, u32, &'static str)]) -> Result<()> {
+    writeln!(
+        file,
+        "
+/// `foreach_peripheral!(($p:ident, $r:ident, $offset:expr, $access:ident) =>
+/// {{ ... }});` expands the body once for every top-level register of every
+/// peripheral module in this device; registers inside clusters are not
+/// listed.
+#[macro_export]
+macro_rules! foreach_peripheral {{
+    ($pat:tt => $code:tt) => {{
+        macro_rules! __foreach_peripheral_inner {{
+            $pat => $code;
+        }}"
+    )?;
+    for (module, register, offset, access_type) in rows {
         writeln!(
-            s,
-            "    pub {}: {}<{}>,",
-            reg_info.name, access_type_name, size_type_name
+            file,
+            "        __foreach_peripheral_inner!({}, {}, {}, {});",
+            module, register, offset, access_type
         )?;
-        register_block.push(s);
+    }
+    writeln!(file, "    }};\n}}")?;
 
-        // ResetValues entry
-        let s = format!("    pub {}: {},", reg_info.name, size_type_name);
-        reset_values.push(s);
+    Ok(())
+}
+
+/// Writes `interrupts.rs`: a `#[repr(u16)]` enum of every interrupt in the
+/// device, numbered to match the vector table. A downstream HAL wires this
+/// into `cortex-m-rt`'s or `riscv-rt`'s vector table via the `Interrupt`
+/// trait impls those crates expect.
+fn write_interrupts(file: &mut fs::File, interrupts: &[ModelInterrupt]) -> Result<()> {
+    writeln!(file, "#![allow(non_camel_case_types)]")?;
+    writeln!(file, "//! Device interrupt numbering\n")?;
+
+    writeln!(file, "/// Enumeration of all the interrupts of this device")?;
+    writeln!(file, "#[derive(Clone, Copy, Debug, PartialEq, Eq)]")?;
+    // A zero-variant enum can't carry an explicit integer repr.
+    if !interrupts.is_empty() {
+        writeln!(file, "#[repr(u16)]")?;
+    }
+    writeln!(file, "pub enum Interrupt {{")?;
+    for interrupt in interrupts {
+        if let Some(description) = interrupt.description.as_ref() {
+            write!(file, "{}", build_doc_comment("    ///", description))?;
+        }
+        writeln!(file, "    {} = {},", interrupt.name, interrupt.value)?;
+    }
+    writeln!(file, "}}\n")?;
+
+    writeln!(file, "/// Name/number pairs for every interrupt, in numeric order")?;
+    writeln!(file, "pub const INTERRUPTS: &[(&str, u16)] = &[")?;
+    for interrupt in interrupts {
+        writeln!(file, "    (\"{}\", {}),", interrupt.name, interrupt.value)?;
+    }
+    writeln!(file, "];")?;
+
+    Ok(())
+}
+
+/// RegisterBlock/ResetValues struct-field lines for one nesting level, plus
+/// any extra `{Cluster}Block`/`{Cluster}ResetValues` struct definitions its
+/// clusters needed.
+struct BlockPieces {
+    register_block_fields: String,
+    reset_values_fields: String,
+    nested_structs: String,
+}
+
+/// Builds the `RegisterBlock`/`ResetValues` struct-field lines for `items`,
+/// recursing into clusters to emit their own `{Cluster}Block`/
+/// `{Cluster}ResetValues` struct pair. `prefix` disambiguates same-named
+/// clusters nested under different parents within one peripheral (e.g. two
+/// clusters that each contain a `CH` cluster).
+fn generate_block_pieces(prefix: &str, items: &[RegisterOrCluster], access_types: &mut BTreeSet<&'static str>) -> BlockPieces {
+    let mut register_block = Vec::new();
+    let mut reset_values = Vec::new();
+    let mut nested_structs = Vec::new();
+
+    for item in items {
+        match item {
+            RegisterOrCluster::Register(reg_info) => {
+                let access_type_name = reg_info.properties.access_type_name();
+                access_types.insert(access_type_name);
+                let size_type_name = reg_info.properties.size_type_name();
+
+                let element_type = format!("{}<{}>", access_type_name, size_type_name);
+                let element_size = reg_info.properties.size / 8;
+                let field_type = match &reg_info.array {
+                    Some(array) if array.is_contiguous(element_size) => {
+                        format!("[{}; {}]", element_type, array.count)
+                    }
+                    Some(array) => {
+                        // `dimIncrement` leaves a gap after each element, so a
+                        // plain `[{element_type}; N]` would mis-place every
+                        // element after the first; pad each one out to the
+                        // real stride instead.
+                        let padded_type = format!("{}{}Element", prefix, reg_info.name);
+                        let padding = array.stride.checked_sub(element_size).unwrap_or_else(|| {
+                            panic!(
+                                "{}: dimIncrement ({}) is smaller than the register size ({}), so elements would overlap",
+                                reg_info.name, array.stride, element_size
+                            )
+                        });
+                        nested_structs.push(format!(
+                            "#[repr(C)]\npub struct {padded_type} {{\n    pub register: {element_type},\n    _reserved: [u8; {padding}],\n}}\n\nimpl ::core::ops::Deref for {padded_type} {{\n    type Target = {element_type};\n    #[inline(always)]\n    fn deref(&self) -> &{element_type} {{\n        &self.register\n    }}\n}}\n",
+                            padded_type = padded_type,
+                            element_type = element_type,
+                            padding = padding,
+                        ));
+                        format!("[{}; {}]", padded_type, array.count)
+                    }
+                    None => element_type,
+                };
+
+                let mut s = String::new();
+                if let Some(description) = reg_info.description.as_ref() {
+                    s += &build_doc_comment("    ///", description);
+                }
+                writeln!(s, "    pub {}: {},", reg_info.name, field_type).unwrap();
+                register_block.push(s);
+
+                let reset_type = match &reg_info.array {
+                    Some(array) => format!("[{}; {}]", size_type_name, array.count),
+                    None => size_type_name.to_string(),
+                };
+                reset_values.push(format!("    pub {}: {},", reg_info.name, reset_type));
+            }
+            RegisterOrCluster::Cluster(cluster) => {
+                let struct_prefix = format!("{}{}", prefix, cluster.name);
+                let block_type = format!("{}Block", struct_prefix);
+                let reset_type = format!("{}ResetValues", struct_prefix);
+
+                let nested = generate_block_pieces(&struct_prefix, &cluster.registers, access_types);
+                nested_structs.push(nested.nested_structs);
+                nested_structs.push(format!(
+                    "#[repr(C)]\npub struct {} {{\n{}\n}}\n",
+                    block_type, nested.register_block_fields
+                ));
+                nested_structs.push(format!(
+                    "#[derive(Clone, Copy)]\npub struct {} {{\n{}\n}}\n",
+                    reset_type, nested.reset_values_fields
+                ));
+
+                let field_block_type = match &cluster.array {
+                    Some(array) => format!("[{}; {}]", block_type, array.count),
+                    None => block_type,
+                };
+                let field_reset_type = match &cluster.array {
+                    Some(array) => format!("[{}; {}]", reset_type, array.count),
+                    None => reset_type,
+                };
+
+                let mut s = String::new();
+                if let Some(description) = cluster.description.as_ref() {
+                    s += &build_doc_comment("    ///", description);
+                }
+                writeln!(s, "    pub {}: {},", cluster.name, field_block_type).unwrap();
+                register_block.push(s);
+
+                reset_values.push(format!("    pub {}: {},", cluster.name, field_reset_type));
+            }
+        }
     }
 
+    BlockPieces {
+        register_block_fields: register_block.join("\n"),
+        reset_values_fields: reset_values.join("\n"),
+        nested_structs: nested_structs.join(""),
+    }
+}
+
+/// Builds the `pub mod NAME { ... }` field-value modules for `items`,
+/// nesting a cluster's registers inside `pub mod {cluster} { ... }`.
+fn generate_value_modules(items: &[RegisterOrCluster]) -> Vec<String> {
+    let mut modules = Vec::new();
+    for item in items {
+        match item {
+            RegisterOrCluster::Register(reg_info) => {
+                let mut code = String::new();
+                if let Some(description) = reg_info.description.as_ref() {
+                    code += &build_doc_comment("///", description);
+                }
+                writeln!(code, "pub mod {} {{", reg_info.name).unwrap();
+                let field_strings: Vec<_> = reg_info.fields.iter().map(|f| f.generate_code()).collect();
+                code += &field_strings.join("\n");
+                writeln!(code, "}}").unwrap();
+                modules.push(code);
+            }
+            RegisterOrCluster::Cluster(cluster) => {
+                let mut code = String::new();
+                if let Some(description) = cluster.description.as_ref() {
+                    code += &build_doc_comment("///", description);
+                }
+                writeln!(code, "pub mod {} {{", cluster.name).unwrap();
+                code += &indent(&generate_value_modules(&cluster.registers).join("\n"), 1);
+                writeln!(code, "\n}}").unwrap();
+                modules.push(code);
+            }
+        }
+    }
+    modules
+}
+
+fn write_peripheral(file: &mut fs::File, peripheral: &ModelPeripheral, config: &GeneratorConfig) -> Result<()> {
+    writeln!(file, "#![allow(non_snake_case, non_upper_case_globals)]")?;
+    writeln!(file, "#![allow(non_camel_case_types)]")?;
+    writeln!(file, "{}", build_doc_comment("//!", &peripheral.description))?;
+
+    let mut access_types = BTreeSet::new();
+    let pieces = generate_block_pieces("", &peripheral.registers, &mut access_types);
+    let register_modules = generate_value_modules(&peripheral.registers);
+
     let mut access_types: Vec<_> = access_types.iter().map(|s| s.to_string()).collect();
     access_types.sort();
-    writeln!(file, "use ral_registers::{{{}}};", access_types.join(", "))?;
+    let register_types_source = match config.backend {
+        RegisterBackend::RalRegisters => "ral_registers".to_string(),
+        RegisterBackend::VolatileCell | RegisterBackend::RawPointer => "super::super::registers".to_string(),
+    };
+    writeln!(file, "use {}::{{{}}};", register_types_source, access_types.join(", "))?;
     writeln!(file, "use core::marker::PhantomData;\n")?;
 
     writeln!(file, "{}", register_modules.join("\n"))?;
 
+    writeln!(file, "{}", pieces.nested_structs)?;
+
+    writeln!(file, "#[repr(C)]")?;
     writeln!(file, "pub struct RegisterBlock {{")?;
-    write!(file, "{}", register_block.join("\n"))?;
+    writeln!(file, "{}", pieces.register_block_fields)?;
     writeln!(file, "}}\n")?;
 
     writeln!(file, "pub struct ResetValues {{")?;
-    writeln!(file, "{}", reset_values.join("\n"))?;
+    writeln!(file, "{}", pieces.reset_values_fields)?;
     writeln!(file, "}}")?;
 
     writeln!(
@@ -261,6 +722,68 @@ impl ::core::ops::Deref for Instance {{
     Ok(())
 }
 
+/// Names to re-export from the peripheral module so single-access macros
+/// (`read_reg!`/`write_reg!`) can find a register's value modules without
+/// qualifying them. Clusters are re-exported by their own module name
+/// instead of flattening their registers, since two clusters (or a cluster
+/// and a top-level register) can share a register name.
+fn reexport_names(entries: &[ResetValueEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| match entry {
+            ResetValueEntry::Register(value) => value.register.clone(),
+            ResetValueEntry::Cluster(cluster) => cluster.name.clone(),
+        })
+        .collect()
+}
+
+/// Names of the `{Cluster}ResetValues` struct types referenced by the
+/// `ResetValues` literal `generate_reset_values` builds, so the instance
+/// module can import them alongside `ResetValues` itself. Recurses with the
+/// same `prefix` naming scheme `generate_block_pieces`/`generate_reset_values`
+/// use, so the names line up with the types actually defined in the
+/// peripheral module.
+fn reset_value_type_names(prefix: &str, entries: &[ResetValueEntry]) -> Vec<String> {
+    let mut names = Vec::new();
+    for entry in entries {
+        if let ResetValueEntry::Cluster(cluster) = entry {
+            let struct_prefix = format!("{}{}", prefix, cluster.name);
+            names.push(format!("{}ResetValues", struct_prefix));
+            names.extend(reset_value_type_names(&struct_prefix, &cluster.entries));
+        }
+    }
+    names
+}
+
+/// Builds the `ResetValues { ... }` literal body, recursing into clusters to
+/// build their `{Cluster}ResetValues { ... }` literal with the exact same
+/// `prefix` naming `generate_block_pieces` used for the struct definitions.
+fn generate_reset_values(prefix: &str, entries: &[ResetValueEntry]) -> String {
+    let mut lines = Vec::new();
+    for entry in entries {
+        match entry {
+            ResetValueEntry::Register(value) => {
+                let literal = match value.count {
+                    Some(count) => format!("[{:#x}; {}]", value.value, count),
+                    None => format!("{:#x}", value.value),
+                };
+                lines.push(format!("        {}: {},", value.register, literal));
+            }
+            ResetValueEntry::Cluster(cluster) => {
+                let struct_prefix = format!("{}{}", prefix, cluster.name);
+                let inner = generate_reset_values(&struct_prefix, &cluster.entries);
+                let element = format!("{}ResetValues {{\n{}\n        }}", struct_prefix, inner);
+                let literal = match &cluster.array {
+                    Some(array) => format!("[{}; {}]", element, array.count),
+                    None => element,
+                };
+                lines.push(format!("        {}: {},", cluster.name, literal));
+            }
+        }
+    }
+    lines.join("\n")
+}
+
 fn write_peripheral_instance(
     file: &mut fs::File,
     instance: &ModelPeripheralInstance,
@@ -278,11 +801,17 @@ fn write_peripheral_instance(
         peripheral_mod
     )?;
 
-    let mut register_types = Vec::new();
-    for value in &instance.reset_values {
-        register_types.push(value.register.clone());
+    let cluster_types = reset_value_type_names("", &instance.reset_values);
+    if !cluster_types.is_empty() {
+        writeln!(
+            file,
+            "pub use super::super::peripherals::{}::{{{}}};",
+            peripheral_mod,
+            cluster_types.join(", ")
+        )?;
     }
 
+    let register_types = reexport_names(&instance.reset_values);
     if !register_types.is_empty() {
         writeln!(
             file,
@@ -293,6 +822,12 @@ fn write_peripheral_instance(
     }
     writeln!(file)?;
 
+    let cluster_type_imports = if cluster_types.is_empty() {
+        String::new()
+    } else {
+        format!("    use super::{{{}}};\n", cluster_types.join(", "))
+    };
+
     write!(
         file,
         "
@@ -300,6 +835,7 @@ fn write_peripheral_instance(
 pub mod {name} {{
     use super::ResetValues;
     use super::Instance;
+{cluster_type_imports}    use core::sync::atomic::{{AtomicBool, Ordering}};
 
     const INSTANCE: Instance = Instance {{
         addr: {:#x},
@@ -310,14 +846,17 @@ pub mod {name} {{
     pub const reset: ResetValues = ResetValues {{
 ",
         instance.base_address,
-        name = instance.name
+        name = instance.name,
+        cluster_type_imports = cluster_type_imports
     )?;
 
-    let mut values = Vec::new();
-    for value in &instance.reset_values {
-        values.push(format!("        {}: {:#x},", value.register, value.value));
-    }
-    write!(file, "{}", values.join("\n"))?;
+    write!(file, "{}", generate_reset_values("", &instance.reset_values))?;
+
+    let critical_section = if config.critical_section {
+        "critical_section::with".to_string()
+    } else {
+        format!("{}::interrupt::free", config.arch_crate)
+    };
 
     writeln!(
         file,
@@ -327,7 +866,7 @@ pub mod {name} {{
     #[allow(renamed_and_removed_lints)]
     #[allow(private_no_mangle_statics)]
     #[no_mangle]
-    static mut {name}_TAKEN: bool = false;
+    static {name}_TAKEN: AtomicBool = AtomicBool::new(false);
 
     /// Safe access to {name}
     ///
@@ -343,11 +882,11 @@ pub mod {name} {{
     /// provides access to the peripheral's registers.
     #[inline]
     pub fn take() -> Option<Instance> {{
-        {arch_crate}::interrupt::free(|_| unsafe {{
-            if {name}_TAKEN {{
+        {critical_section}(|_| {{
+            if {name}_TAKEN.load(Ordering::Relaxed) {{
                 None
             }} else {{
-                {name}_TAKEN = true;
+                {name}_TAKEN.store(true, Ordering::Relaxed);
                 Some(INSTANCE)
             }}
         }})
@@ -361,9 +900,9 @@ pub mod {name} {{
     /// already taken.
     #[inline]
     pub fn release(inst: Instance) {{
-        {arch_crate}::interrupt::free(|_| unsafe {{
-            if {name}_TAKEN && inst.addr == INSTANCE.addr {{
-                {name}_TAKEN = false;
+        {critical_section}(|_| {{
+            if {name}_TAKEN.load(Ordering::Relaxed) && inst.addr == INSTANCE.addr {{
+                {name}_TAKEN.store(false, Ordering::Relaxed);
             }} else {{
                 panic!(\"Released a peripheral which was not taken\");
             }}
@@ -378,7 +917,7 @@ pub mod {name} {{
     #[allow(clippy::missing_safety_doc)]
     #[inline]
     pub unsafe fn steal() -> Instance {{
-        {name}_TAKEN = true;
+        {name}_TAKEN.store(true, Ordering::Relaxed);
         INSTANCE
     }}
 
@@ -405,13 +944,34 @@ pub mod {name} {{
 /// simply call for example `write_reg!(gpio, GPIOA, ODR, 1);`.
 pub const {name}: *const RegisterBlock = {:#x} as *const _;",
         instance.base_address,
-        arch_crate = config.arch_crate,
+        critical_section = critical_section,
         name = instance.name
     )?;
 
     Ok(())
 }
 
+impl FinalFieldInfo {
+    /// Renders the `pub const NAME: u32 = ...;` lines for every enumerated
+    /// value whose usage matches `usage`. `ReadWrite` values are additionally
+    /// included under `Read` and `Write` so single-access macros can find
+    /// them regardless of the register's declared access type.
+    fn value_consts(&self, usage: ValueUsage) -> Vec<String> {
+        self.enumerated_values
+            .iter()
+            .filter(|value| value.usage == usage || value.usage == ValueUsage::ReadWrite)
+            .map(|value| {
+                let mut s = String::new();
+                if let Some(description) = value.description.as_ref() {
+                    s += &build_doc_comment("        ///", description);
+                }
+                writeln!(s, "        pub const {}: u32 = {:#x};", value.name, value.value).unwrap();
+                s
+            })
+            .collect()
+    }
+}
+
 trait Codegen {
     fn generate_code(&self) -> String;
 }
@@ -428,7 +988,6 @@ impl Codegen for FinalFieldInfo {
         writeln!(code, "    /// Offset ({} bits)", self.bit_range.offset).unwrap();
         writeln!(code, "    pub const offset: u32 = {};", self.bit_range.offset).unwrap();
 
-        eprintln!("{} ({})", self.bit_range.width, self.name);
         let mask = (1u64 << self.bit_range.width) - 1;
         write!(
             code,
@@ -440,18 +999,24 @@ impl Codegen for FinalFieldInfo {
         )
         .unwrap();
 
-        writeln!(
-            code,
-            "
-    /// Read-only values (empty)
-    pub mod R {{}}
-    /// Write-only values (empty)
-    pub mod W {{}}
-    /// Read-write values (empty)
-    pub mod RW {{}}
-"
-        )
-        .unwrap();
+        let r = self.value_consts(ValueUsage::Read);
+        let w = self.value_consts(ValueUsage::Write);
+        let rw = self.value_consts(ValueUsage::ReadWrite);
+
+        writeln!(code, "\n    /// Read-only values{}", if r.is_empty() { " (empty)" } else { "" }).unwrap();
+        writeln!(code, "    pub mod R {{").unwrap();
+        write!(code, "{}", r.join("\n")).unwrap();
+        writeln!(code, "    }}").unwrap();
+
+        writeln!(code, "    /// Write-only values{}", if w.is_empty() { " (empty)" } else { "" }).unwrap();
+        writeln!(code, "    pub mod W {{").unwrap();
+        write!(code, "{}", w.join("\n")).unwrap();
+        writeln!(code, "    }}").unwrap();
+
+        writeln!(code, "    /// Read-write values{}", if rw.is_empty() { " (empty)" } else { "" }).unwrap();
+        writeln!(code, "    pub mod RW {{").unwrap();
+        write!(code, "{}", rw.join("\n")).unwrap();
+        writeln!(code, "    }}").unwrap();
 
         writeln!(code, "}}").unwrap();
 
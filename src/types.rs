@@ -0,0 +1,249 @@
+//! Intermediate representation produced by [`crate::convert::convert`].
+//!
+//! The SVD object model from `svd_parser` is fairly irregular (optional
+//! `derivedFrom`, register/cluster arrays, etc). `convert` flattens that
+//! into the plain structures below so that the code generator in `lib.rs`
+//! never has to deal with SVD quirks directly. Register access, size and
+//! reset value are taken from each register alone (defaulting to
+//! read-write, 32 bits and 0); peripheral- and device-level default
+//! register properties are not applied.
+
+pub struct ModelDevice {
+    pub peripherals: Vec<ModelPeripheral>,
+    pub instances: Vec<ModelPeripheralInstance>,
+    pub interrupts: Vec<ModelInterrupt>,
+}
+
+pub struct ModelInterrupt {
+    pub name: String,
+    pub value: u16,
+    pub description: Option<String>,
+}
+
+pub struct ModelPeripheral {
+    pub name: String,
+    pub module_name: String,
+    pub description: String,
+    pub registers: Vec<RegisterOrCluster>,
+}
+
+/// A register or a cluster of registers, mirroring SVD's
+/// `<registers>` children (`<register>` / `<cluster>`).
+pub enum RegisterOrCluster {
+    Register(FinalRegisterInfo),
+    Cluster(FinalClusterInfo),
+}
+
+impl RegisterOrCluster {
+    pub fn name(&self) -> &str {
+        match self {
+            RegisterOrCluster::Register(r) => &r.name,
+            RegisterOrCluster::Cluster(c) => &c.name,
+        }
+    }
+
+    pub fn offset(&self) -> u32 {
+        match self {
+            RegisterOrCluster::Register(r) => r.offset,
+            RegisterOrCluster::Cluster(c) => c.offset,
+        }
+    }
+}
+
+/// Describes an SVD `dim`/`dimIncrement` repetition of a register or
+/// cluster.
+pub struct ArrayInfo {
+    pub count: u32,
+    /// `dimIncrement`: byte stride between successive elements.
+    pub stride: u32,
+}
+
+impl ArrayInfo {
+    /// Whether successive elements sit back-to-back with no gap, so a
+    /// plain Rust array of the element type can be used instead of a
+    /// padded wrapper struct.
+    pub fn is_contiguous(&self, element_size: u32) -> bool {
+        self.stride == element_size
+    }
+}
+
+pub struct FinalClusterInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub offset: u32,
+    pub registers: Vec<RegisterOrCluster>,
+    pub array: Option<ArrayInfo>,
+}
+
+pub struct ModelPeripheralInstance {
+    pub name: String,
+    pub module_name: String,
+    pub description: String,
+    pub peripheral_module: String,
+    pub base_address: u64,
+    pub reset_values: Vec<ResetValueEntry>,
+}
+
+/// Reset value for one register, or the reset values for a whole cluster.
+/// Mirrors [`RegisterOrCluster`] so the `ResetValues` struct and its literal
+/// initializer can be built with the exact same shape as `RegisterBlock`.
+pub enum ResetValueEntry {
+    Register(ResetValue),
+    Cluster(ClusterResetValues),
+}
+
+pub struct ResetValue {
+    pub register: String,
+    pub value: u64,
+    /// Number of elements if `register` is an array field, so
+    /// `write_peripheral_instance` can emit a `[value; N]` initializer
+    /// instead of a scalar one.
+    pub count: Option<u32>,
+}
+
+pub struct ClusterResetValues {
+    pub name: String,
+    pub array: Option<ArrayInfo>,
+    pub entries: Vec<ResetValueEntry>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccessType {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+pub struct RegisterProperties {
+    pub access: AccessType,
+    pub size: u32,
+}
+
+impl RegisterProperties {
+    pub fn access_type_name(&self) -> &'static str {
+        match self.access {
+            AccessType::ReadOnly => "RORegister",
+            AccessType::WriteOnly => "WORegister",
+            AccessType::ReadWrite => "RWRegister",
+        }
+    }
+
+    pub fn size_type_name(&self) -> &'static str {
+        match self.size {
+            8 => "u8",
+            16 => "u16",
+            32 => "u32",
+            64 => "u64",
+            _ => "u32",
+        }
+    }
+}
+
+pub struct FinalRegisterInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub offset: u32,
+    pub properties: RegisterProperties,
+    pub fields: Vec<FinalFieldInfo>,
+    pub array: Option<ArrayInfo>,
+}
+
+pub struct BitRange {
+    pub offset: u32,
+    pub width: u32,
+}
+
+/// Which access path an enumerated value is valid for.
+///
+/// Mirrors the SVD `<enumeratedValues usage="...">` attribute. `ReadWrite`
+/// values are additionally mirrored into `R` and `W` so that single-access
+/// macros (`read_reg!`, `write_reg!`) can find them without knowing the
+/// register's real access type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueUsage {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+pub struct FinalEnumeratedValue {
+    pub name: String,
+    pub description: Option<String>,
+    pub value: u64,
+    pub usage: ValueUsage,
+}
+
+pub struct FinalFieldInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub bit_range: BitRange,
+    pub enumerated_values: Vec<FinalEnumeratedValue>,
+}
+
+impl ModelPeripheral {
+    /// Structural fingerprint of this peripheral's register layout.
+    ///
+    /// Two peripherals with the same fingerprint are byte-identical except
+    /// for their own name/base address/top-level description, so `generate`
+    /// can emit their register-definition module once and share it. Register
+    /// and field descriptions are included: two peripherals that differ only
+    /// in per-register/field prose would otherwise be merged onto one
+    /// module, silently discarding one side's documentation.
+    pub fn fingerprint(&self) -> String {
+        let mut s = String::new();
+        fingerprint_items(&self.registers, &mut s);
+        s
+    }
+}
+
+fn fingerprint_items(items: &[RegisterOrCluster], s: &mut String) {
+    use std::fmt::Write as _;
+
+    for item in items {
+        match item {
+            RegisterOrCluster::Register(register) => {
+                writeln!(
+                    s,
+                    "{}@{}:{}:{}:{:?}:{:?}",
+                    register.name,
+                    register.offset,
+                    register.properties.access_type_name(),
+                    register.properties.size,
+                    register.array.as_ref().map(|a| (a.count, a.stride)),
+                    register.description
+                )
+                .unwrap();
+                for field in &register.fields {
+                    write!(
+                        s,
+                        "  {}@{}:{}:{:?}",
+                        field.name, field.bit_range.offset, field.bit_range.width, field.description
+                    )
+                    .unwrap();
+                    for value in &field.enumerated_values {
+                        write!(
+                            s,
+                            " {}={:#x}:{:?}:{:?}",
+                            value.name, value.value, value.usage, value.description
+                        )
+                        .unwrap();
+                    }
+                    s.push('\n');
+                }
+            }
+            RegisterOrCluster::Cluster(cluster) => {
+                writeln!(
+                    s,
+                    "cluster {}@{}:{:?}:{:?} {{",
+                    cluster.name,
+                    cluster.offset,
+                    cluster.array.as_ref().map(|a| (a.count, a.stride)),
+                    cluster.description
+                )
+                .unwrap();
+                fingerprint_items(&cluster.registers, s);
+                writeln!(s, "}}").unwrap();
+            }
+        }
+    }
+}